@@ -0,0 +1,69 @@
+//! A typed message channel built on top of `Pipe`.
+
+use crate::{Error, Pipe, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+
+/// Size, in bytes, of the little-endian length header prefixed to every message.
+const LENGTH_PREFIX_SIZE: usize = 4;
+
+/// Largest payload `Channel::recv` will allocate a buffer for. The length
+/// prefix comes off the wire unchecked, so without a cap a corrupted or
+/// desynced frame could otherwise trigger an allocation up to `u32::MAX`
+/// bytes.
+pub(crate) const MAX_PAYLOAD_SIZE: usize = 64 * 1024 * 1024;
+
+/// A typed message channel built on top of a `Pipe`.
+///
+/// Each call to [`Channel::send`] writes a whole `T`, serialized with
+/// `bincode` and prefixed with a fixed-width little-endian length header.
+/// [`Channel::recv`] reads the header, then reads exactly that many payload
+/// bytes before deserializing, looping internally as needed so a short read
+/// never yields a truncated message.
+pub struct Channel<T>
+{
+    pipe: Pipe,
+    _marker: PhantomData<T>
+}
+
+impl<T> Channel<T>
+where
+    T: Serialize + DeserializeOwned
+{
+    /// Wraps an existing `Pipe` in a typed channel.
+    pub fn new(pipe: Pipe) -> Self
+    {
+        Channel { pipe, _marker: PhantomData }
+    }
+
+    /// Serializes `value` and writes it as a single length-framed message.
+    pub fn send(&mut self, value: &T) -> Result<()>
+    {
+        let payload = bincode::serialize(value)?;
+        let len = payload.len() as u32;
+
+        self.pipe.write_bytes(&len.to_le_bytes())?;
+        self.pipe.write_bytes(&payload)?;
+        Ok(())
+    }
+
+    /// Reads a single length-framed message and deserializes it.
+    ///
+    /// Rejects a length prefix greater than [`MAX_PAYLOAD_SIZE`] with
+    /// [`Error::FrameTooLarge`] before allocating a buffer for it, since the
+    /// prefix comes off the wire unchecked.
+    pub fn recv(&mut self) -> Result<T>
+    {
+        let header = self.pipe.read_bytes(LENGTH_PREFIX_SIZE)?;
+        let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+
+        if len > MAX_PAYLOAD_SIZE
+        {
+            return Err(Error::FrameTooLarge(len));
+        }
+
+        let payload = self.pipe.read_bytes(len)?;
+        bincode::deserialize(&payload).map_err(Error::from)
+    }
+}
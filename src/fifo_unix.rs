@@ -0,0 +1,438 @@
+use super::{Result, Error, OnCleanup, PipeMode};
+use std::{io::Read, io::Write, path::Path};
+use std::fs::{File, OpenOptions};
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::AsRawFd;
+use rand::{thread_rng, Rng, distributions::Alphanumeric};
+
+/// Abstraction over a named pipe
+pub struct Pipe
+{
+    handle: Option<File>,
+    listener: Option<File>,
+    pub(super) path: std::path::PathBuf,
+    pub(super) is_closed: bool,
+    delete_on_drop: bool
+}
+
+impl Pipe
+{
+    /// Open an existing pipe. If 'delete_on_drop' is true, the named pipe will
+    /// be deleted when the returned struct is deallocated.
+    pub fn open(path: &Path, delete_on_drop: OnCleanup) -> Result<Self>
+    {
+        if !path.exists()
+        {
+            mkfifo(path)?;
+        }
+
+        Ok(Pipe
+        {
+            handle: None,
+            listener: None,
+            path: path.to_path_buf(),
+            is_closed: false,
+            delete_on_drop: delete_on_drop == OnCleanup::DeleteOnDrop
+        })
+    }
+
+    /// Create a pipe. If 'delete_on_drop' is true, the named pipe will be
+    /// deleted when the returned struct is deallocated.
+    pub fn create(delete_on_drop: OnCleanup) -> Result<Self>
+    {
+        // Generate a random path name
+        let path_string = format!("/tmp/pipe_{}_{}", std::process::id(), thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(15)
+            .collect::<String>());
+
+        Pipe::open(Path::new(&path_string), delete_on_drop)
+    }
+
+    /// Opens an existing pipe for [`PipeBuilder`](super::PipeBuilder).
+    ///
+    /// `mode` is accepted for API symmetry with Windows but has no effect
+    /// here: Unix named pipes are always byte streams.
+    pub(crate) fn open_with(path: &Path, on_cleanup: OnCleanup, _mode: PipeMode) -> Result<Self>
+    {
+        Pipe::open(path, on_cleanup)
+    }
+
+    /// Creates a new pipe for [`PipeBuilder`](super::PipeBuilder).
+    ///
+    /// `mode` is accepted for API symmetry with Windows but has no effect
+    /// here: Unix named pipes are always byte streams.
+    pub(crate) fn create_with(on_cleanup: OnCleanup, _mode: PipeMode) -> Result<Self>
+    {
+        Pipe::create(on_cleanup)
+    }
+
+    /// Close the pipe. If the pipe is not closed before deallocation, this will
+    /// be called automatically on drop.
+    pub fn close(&mut self) -> Result<()>
+    {
+        self.is_closed = true;
+        self.handle = None;
+        self.listener = None;
+        if self.delete_on_drop
+        {
+            let _ = std::fs::remove_file(&self.path);
+        }
+        Ok(())
+    }
+
+    /// Write a single byte to the pipe
+    pub fn write_byte(&mut self, buf: u8) -> Result<usize>
+    {
+        self.write_bytes(&[buf])
+    }
+
+    /// Write an array of bytes to the pipe.
+    ///
+    /// Loops internally until the whole buffer has been written, since a
+    /// single `write` to a pipe can legally return fewer bytes than
+    /// requested once it exceeds the kernel buffer's available space.
+    pub fn write_bytes(&mut self, buf: &[u8]) -> Result<usize>
+    {
+        self.init_reader()?;
+        match &mut self.handle
+        {
+            None => unreachable!(),
+            Some(stream) => stream.write_all(buf)
+        }.map(|()| buf.len()).map_err(Error::from)
+    }
+
+    /// Writes a string to the pipe
+    pub fn write_string(&mut self, s: &str) -> Result<usize>
+    {
+        self.init_reader()?;
+        self.write_bytes(s.as_bytes())
+    }
+
+    /// Read a single byte
+    pub fn read_byte(&mut self) -> Result<u8>
+    {
+        self.init_listener()?;
+        match &mut self.listener
+        {
+            None => unreachable!(),
+            Some(listener) =>
+            {
+                let buf = &mut [0_u8];
+                listener.read(buf).map_err(Error::from)?;
+                Ok(buf[0])
+            }
+        }
+    }
+
+    /// Reads the given number of bytes and returns the result in a vector.
+    pub fn read_bytes(&mut self, size: usize) -> Result<Vec<u8>>
+    {
+        self.init_listener()?;
+        match &mut self.listener
+        {
+            None => unreachable!(),
+            Some(listener) =>
+            {
+                let mut buf = vec![0u8; size];
+                match listener.read_exact(&mut buf)
+                {
+                    Ok(()) => Ok(buf),
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Err(Error::UnexpectedEof),
+                    Err(e) => Err(Error::from(e))
+                }
+            }
+        }
+    }
+
+    /// Reads the given number of bytes and returns the result as a string.
+    pub fn read_string(&mut self, size: usize) -> Result<String>
+    {
+        self.read_bytes(size).map(|buf| String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    /// Flush input and output.
+    pub fn flush_pipe(&mut self) -> Result<()>
+    {
+        // Flush output
+        match &mut self.handle
+        {
+            None =>
+            {
+                self.init_reader()?;
+            }
+            Some(stream) =>
+            {
+                stream.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Initializes the pipe for writing
+    fn init_reader(&mut self) -> Result<()>
+    {
+        if self.handle.is_none()
+        {
+            self.handle = Some(OpenOptions::new().write(true).open(&self.path)?);
+        }
+        Ok(())
+    }
+
+    /// Initializes the pipe for reading
+    fn init_listener(&mut self) -> Result<()>
+    {
+        if self.listener.is_none()
+        {
+            self.listener = Some(OpenOptions::new().read(true).open(&self.path)?);
+        }
+        Ok(())
+    }
+
+    /// Connects both ends of the pipe, as `split()` and `set_nonblocking()`
+    /// need. Opening a FIFO for writing blocks until a reader opens it (and
+    /// vice versa), so if neither end is connected yet, opening them one
+    /// after another on this thread would deadlock forever: nothing else
+    /// would ever open the other end. Instead, when both are missing, one
+    /// end is opened on a background thread so the two opens can rendezvous,
+    /// mirroring `pair()`.
+    fn connect_both(&mut self) -> Result<()>
+    {
+        if self.handle.is_none() && self.listener.is_none()
+        {
+            let writer_path = self.path.clone();
+            let writer_thread = std::thread::spawn(move ||
+            {
+                OpenOptions::new().write(true).open(&writer_path)
+            });
+
+            // Join the writer thread before propagating a failed foreground
+            // open: if the writer connected, its open would otherwise block
+            // forever waiting for a reader that will never arrive.
+            let listener_result = OpenOptions::new().read(true).open(&self.path);
+            let writer_result = writer_thread.join().expect("writer thread panicked");
+
+            self.listener = Some(listener_result?);
+            self.handle = Some(writer_result.map_err(Error::from)?);
+        }
+        else
+        {
+            self.init_reader()?;
+            self.init_listener()?;
+        }
+
+        Ok(())
+    }
+
+    /// Splits the pipe into independent read and write halves, connecting
+    /// both ends if they are not already connected. The returned
+    /// `PipeWriter` takes over responsibility for deleting the named pipe
+    /// from the filesystem on drop; the returned `PipeReader` does not.
+    pub fn split(mut self) -> Result<(PipeReader, PipeWriter)>
+    {
+        self.connect_both()?;
+
+        let listener = self.listener.take().unwrap();
+        let handle = self.handle.take().unwrap();
+        let path = self.path.clone();
+        let delete_on_drop = self.delete_on_drop;
+
+        // Ownership of the path's lifecycle has moved to the `PipeWriter`.
+        self.is_closed = true;
+
+        Ok((
+            PipeReader { listener },
+            PipeWriter { handle, path, delete_on_drop }
+        ))
+    }
+
+    /// Atomically creates a named pipe and connects both ends, returning a
+    /// guaranteed-connected duplex immediately. Unlike `create()` followed by
+    /// a manual read/write, there is no window where only one end has been
+    /// initialized.
+    pub fn pair() -> Result<(PipeReader, PipeWriter)>
+    {
+        let path_string = format!("/tmp/pipe_{}_{}", std::process::id(), thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(15)
+            .collect::<String>());
+        let path = std::path::PathBuf::from(path_string);
+
+        mkfifo(&path)?;
+
+        let writer_path = path.clone();
+        let writer_thread = std::thread::spawn(move ||
+        {
+            OpenOptions::new().write(true).open(&writer_path)
+        });
+
+        // Join the writer thread before propagating a failed foreground open,
+        // rather than abandoning it blocked on an open that will now never
+        // be matched.
+        let listener_result = OpenOptions::new().read(true).open(&path);
+        let writer_result = writer_thread.join().expect("writer thread panicked");
+
+        let listener = listener_result?;
+        let handle = writer_result.map_err(Error::from)?;
+
+        Ok((
+            PipeReader { listener },
+            PipeWriter { handle, path, delete_on_drop: true }
+        ))
+    }
+
+    /// Puts the pipe into (or takes it out of) non-blocking mode.
+    ///
+    /// Connects both ends first if they are not already connected. Once set,
+    /// reads that would otherwise block return `ErrorKind::WouldBlock`
+    /// instead.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()>
+    {
+        self.connect_both()?;
+
+        set_fd_nonblocking(self.handle.as_ref().unwrap().as_raw_fd(), nonblocking)?;
+        set_fd_nonblocking(self.listener.as_ref().unwrap().as_raw_fd(), nonblocking)?;
+        Ok(())
+    }
+}
+
+/// Sets or clears `O_NONBLOCK` on `fd`.
+fn set_fd_nonblocking(fd: std::os::unix::io::RawFd, nonblocking: bool) -> Result<()>
+{
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL, 0) };
+    if flags < 0
+    {
+        return Err(Error::from(std::io::Error::last_os_error()));
+    }
+
+    let flags = if nonblocking { flags | libc::O_NONBLOCK } else { flags & !libc::O_NONBLOCK };
+
+    if unsafe { libc::fcntl(fd, libc::F_SETFL, flags) } < 0
+    {
+        return Err(Error::from(std::io::Error::last_os_error()));
+    }
+
+    Ok(())
+}
+
+/// Creates the named pipe backing `path` on the filesystem.
+fn mkfifo(path: &Path) -> Result<()>
+{
+    let path_cstr = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| Error::from(std::io::Error::new(std::io::ErrorKind::InvalidInput, "path contains a null byte")))?;
+
+    let result = unsafe { libc::mkfifo(path_cstr.as_ptr(), 0o644) };
+    if result != 0
+    {
+        return Err(Error::from(std::io::Error::last_os_error()));
+    }
+
+    Ok(())
+}
+
+impl std::io::Read for Pipe
+{
+    fn read(&mut self, bytes: &mut [u8]) -> std::io::Result<usize>
+    {
+        self.init_listener()?;
+        match &mut self.listener
+        {
+            None => unreachable!(),
+            Some(listener) => listener.read(bytes)
+        }
+    }
+}
+
+impl Drop for Pipe
+{
+    fn drop(&mut self)
+    {
+        if !self.is_closed
+        {
+            if let Err(e) = self.close()
+            {
+                eprintln!("Error closing pipe: {:?}", e)
+            }
+        }
+    }
+}
+
+impl Clone for Pipe
+{
+    /// Cloning a pipe creates a slave which points to the same path but does not
+    /// close the pipe when dropped.
+    fn clone(&self) -> Self
+    {
+        Pipe
+        {
+            handle: None,
+            listener: None,
+            path: self.path.clone(),
+            is_closed: true,
+            delete_on_drop: false
+        }
+    }
+}
+
+/// The read half of a `Pipe`, obtained via [`Pipe::split`].
+pub struct PipeReader
+{
+    listener: File
+}
+
+impl PipeReader
+{
+    /// Puts the reader into (or takes it out of) non-blocking mode. Once set,
+    /// reads that would otherwise block return `ErrorKind::WouldBlock`
+    /// instead, the same as [`Pipe::set_nonblocking`].
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()>
+    {
+        set_fd_nonblocking(self.listener.as_raw_fd(), nonblocking)
+    }
+}
+
+impl std::io::Read for PipeReader
+{
+    fn read(&mut self, bytes: &mut [u8]) -> std::io::Result<usize>
+    {
+        self.listener.read(bytes)
+    }
+}
+
+/// The write half of a `Pipe`, obtained via [`Pipe::split`].
+///
+/// This half owns the underlying path and is responsible for deleting the
+/// named pipe from the filesystem on drop if it was created with
+/// `OnCleanup::DeleteOnDrop`.
+pub struct PipeWriter
+{
+    handle: File,
+    path: std::path::PathBuf,
+    delete_on_drop: bool
+}
+
+impl std::io::Write for PipeWriter
+{
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize>
+    {
+        self.handle.write(bytes)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()>
+    {
+        self.handle.flush()
+    }
+}
+
+impl Drop for PipeWriter
+{
+    fn drop(&mut self)
+    {
+        if self.delete_on_drop
+        {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
@@ -1,7 +1,9 @@
-use super::{Result, Error, OnCleanup};
+use super::{Result, Error, OnCleanup, PipeMode};
 use std::{io::Read, path::Path};
 use windows_named_pipe::{PipeStream, PipeListener};
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use rand::{thread_rng, Rng, distributions::Alphanumeric};
 
 /// Abstraction over a named pipe
@@ -10,7 +12,10 @@ pub struct Pipe
     handle: Option<PipeStream>,
     listener: Option<PipeStream>,
     pub(super) path: std::path::PathBuf,
-    pub(super) is_closed: bool
+    pub(super) is_closed: bool,
+    nonblocking: bool,
+    delete_on_drop: bool,
+    mode: PipeMode
 }
 
 unsafe impl Send for Pipe {}
@@ -20,20 +25,35 @@ impl Pipe
 {
     /// Open an existing pipe. If 'delete_on_drop' is true, the named pipe will
     /// be deleted when the returned struct is deallocated.
-    pub fn open(path: &Path, _: OnCleanup) -> Result<Self>
+    pub fn open(path: &Path, delete_on_drop: OnCleanup) -> Result<Self>
     {
-        Ok(Pipe 
-        { 
-            handle: None,
-            listener: None,
-            path: path.to_path_buf(), 
-            is_closed: false
-        })
+        Pipe::open_with(path, delete_on_drop, PipeMode::Byte)
     }
 
     /// Create a pipe. If 'delete_on_drop' is true, the named pipe will be
     /// deleted when the returned struct is deallocated.
     pub fn create(delete_on_drop: OnCleanup) -> Result<Self>
+    {
+        Pipe::create_with(delete_on_drop, PipeMode::Byte)
+    }
+
+    /// Opens an existing pipe for [`PipeBuilder`](super::PipeBuilder).
+    pub(crate) fn open_with(path: &Path, on_cleanup: OnCleanup, mode: PipeMode) -> Result<Self>
+    {
+        Ok(Pipe
+        {
+            handle: None,
+            listener: None,
+            path: path.to_path_buf(),
+            is_closed: false,
+            nonblocking: false,
+            delete_on_drop: on_cleanup == OnCleanup::DeleteOnDrop,
+            mode
+        })
+    }
+
+    /// Creates a new pipe for [`PipeBuilder`](super::PipeBuilder).
+    pub(crate) fn create_with(on_cleanup: OnCleanup, mode: PipeMode) -> Result<Self>
     {
         // Generate a random path name
         let path_string = format!("\\\\.\\pipe\\pipe_{}_{}", std::process::id(),thread_rng()
@@ -41,7 +61,7 @@ impl Pipe
             .take(15)
             .collect::<String>());
 
-        Pipe::open(&Path::new(&path_string), delete_on_drop)
+        Pipe::open_with(&Path::new(&path_string), on_cleanup, mode)
     }
 
     /// Close the pipe. If the pipe is not closed before deallocation, this will
@@ -49,6 +69,15 @@ impl Pipe
     pub fn close(&mut self) -> Result<()>
     {
         self.is_closed = true;
+
+        if self.delete_on_drop
+        {
+            if let Some(listener) = &self.listener
+            {
+                let _ = disconnect(listener);
+            }
+        }
+
         self.handle = None;
         self.listener = None;
         Ok(())
@@ -60,15 +89,19 @@ impl Pipe
         self.write_bytes(&[buf])
     }
 
-    /// Write an array of bytes to the pipe
+    /// Write an array of bytes to the pipe.
+    ///
+    /// Loops internally until the whole buffer has been written, since a
+    /// single `write` to a pipe can legally return fewer bytes than
+    /// requested once it exceeds the pipe buffer's available space.
     pub fn write_bytes(&mut self, buf: &[u8]) -> Result<usize>
     {
         self.init_reader()?;
         match &mut self.handle
         {
             None => unreachable!(),
-            Some(stream) => stream.write(buf)
-        }.map_err(Error::from)
+            Some(stream) => stream.write_all(buf)
+        }.map(|()| buf.len()).map_err(Error::from)
     }
 
     /// Writes a string to the pipe
@@ -114,24 +147,24 @@ impl Pipe
         match &mut self.listener
         {
             None => unreachable!(),
-            Some(listener) => 
+            Some(listener) =>
             {
-                let mut buf = Vec::with_capacity(size);
+                let mut buf = vec![0u8; size];
                 match listener.read_exact(&mut buf)
                 {
-                    Err(e) => 
+                    Ok(()) => Ok(buf),
+                    Err(e) =>
                     {
-                        if let Some(err) = e.raw_os_error()
+                        if e.kind() == std::io::ErrorKind::UnexpectedEof || e.raw_os_error() == Some(109)
                         {
-                            if err as u32 != 109
-                            {
-                                return Err(Error::from(e));
-                            }
+                            Err(Error::UnexpectedEof)
                         }
-                    },
-                    _ => ()
+                        else
+                        {
+                            Err(Error::from(e))
+                        }
+                    }
                 }
-                Ok(buf)
             }
         }
     }
@@ -184,26 +217,143 @@ impl Pipe
     {
         if self.listener.is_none()
         {
-            let listener = PipeListener::bind(&self.path).and_then(|mut ls| ls.accept()).map_err(Error::from)?;
+            let listener = bind(&self.path, self.mode)?;
             self.listener = Some(listener);
         }
         Ok(())
     }
+
+    /// Connects both ends of the pipe, as `split()` and `set_nonblocking()`
+    /// need. A named pipe server instance only finishes binding once a
+    /// client connects (and `PipeStream::connect` blocks until a server is
+    /// listening), so if neither end is connected yet, connecting them one
+    /// after another on this thread would deadlock forever: nothing else
+    /// would ever connect the other end. Instead, when both are missing, the
+    /// client side is connected on a background thread so the two ends can
+    /// rendezvous, mirroring `pair()`.
+    fn connect_both(&mut self) -> Result<()>
+    {
+        if self.handle.is_none() && self.listener.is_none()
+        {
+            let client_path = self.path.clone();
+            let client_thread = std::thread::spawn(move || PipeStream::connect(&client_path));
+
+            // Join the client thread before propagating a failed foreground
+            // bind: if the client connected, it would otherwise be left
+            // blocked forever waiting for a server instance that will never
+            // bind.
+            let listener_result = bind(&self.path, self.mode);
+            let client_result = client_thread.join().expect("client thread panicked");
+
+            self.listener = Some(listener_result?);
+            self.handle = Some(client_result.map_err(Error::from)?);
+        }
+        else
+        {
+            self.init_reader()?;
+            self.init_listener()?;
+        }
+
+        Ok(())
+    }
+
+    /// Splits the pipe into independent read and write halves, connecting
+    /// both ends if they are not already connected. Both halves share
+    /// ownership of the underlying pipe instance and carry this pipe's
+    /// `delete_on_drop` setting; if it is set, whichever one is dropped
+    /// first disconnects the instance.
+    pub fn split(mut self) -> Result<(PipeReader, PipeWriter)>
+    {
+        self.connect_both()?;
+
+        let listener = Arc::new(Mutex::new(self.listener.take().unwrap()));
+        let handle = self.handle.take().unwrap();
+        let path = self.path.clone();
+        let delete_on_drop = self.delete_on_drop;
+        let disconnected = Arc::new(AtomicBool::new(false));
+
+        // Ownership of the path's lifecycle has moved to the two halves.
+        self.is_closed = true;
+
+        Ok((
+            PipeReader { listener: listener.clone(), delete_on_drop, disconnected: disconnected.clone(), nonblocking: false },
+            PipeWriter { handle: Some(handle), path, delete_on_drop, listener, disconnected }
+        ))
+    }
+
+    /// Atomically creates a named pipe and connects both ends, returning a
+    /// guaranteed-connected duplex immediately. Unlike `create()` followed by
+    /// a manual read/write, there is no window where only one end has been
+    /// initialized. Both returned halves delete the pipe instance on drop.
+    pub fn pair() -> Result<(PipeReader, PipeWriter)>
+    {
+        let path_string = format!("\\\\.\\pipe\\pipe_{}_{}", std::process::id(), thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(15)
+            .collect::<String>());
+        let path = std::path::PathBuf::from(path_string);
+
+        let mut listener = PipeListener::bind(&path).map_err(Error::from)?;
+
+        let client_path = path.clone();
+        let client_thread = std::thread::spawn(move || PipeStream::connect(&client_path));
+
+        // Join the client thread before propagating a failed accept, rather
+        // than abandoning it blocked on a connect that will now never be
+        // accepted.
+        let listener_result = listener.accept();
+        let client_result = client_thread.join().expect("client thread panicked");
+
+        let listener = listener_result.map_err(Error::from)?;
+        let handle = client_result.map_err(Error::from)?;
+        let listener = Arc::new(Mutex::new(listener));
+        let disconnected = Arc::new(AtomicBool::new(false));
+
+        Ok((
+            PipeReader { listener: listener.clone(), delete_on_drop: true, disconnected: disconnected.clone(), nonblocking: false },
+            PipeWriter { handle: Some(handle), path, delete_on_drop: true, listener, disconnected }
+        ))
+    }
+
+    /// Puts the pipe into (or takes it out of) non-blocking mode.
+    ///
+    /// Connects both ends first if they are not already connected. Windows
+    /// named pipes have no first-class non-blocking read mode for byte-stream
+    /// instances, so once set, reads peek the pipe's buffer with
+    /// `PeekNamedPipe` first and return `ErrorKind::WouldBlock` instead of
+    /// blocking when no data is available yet.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()>
+    {
+        self.connect_both()?;
+        self.nonblocking = nonblocking;
+        Ok(())
+    }
 }
 
 impl std::io::Read for Pipe
 {
-    fn read(&mut self, bytes: &mut [u8]) -> std::io::Result<usize> 
+    fn read(&mut self, bytes: &mut [u8]) -> std::io::Result<usize>
     {
         self.init_listener()?;
         match &mut self.listener
         {
             None => unreachable!(),
-            Some(listener) => 
+            Some(listener) =>
             {
+                if self.nonblocking
+                {
+                    match peek_available(listener)
+                    {
+                        Ok(0) => return Err(std::io::Error::from(std::io::ErrorKind::WouldBlock)),
+                        Ok(_) => {},
+                        Err(e) if e.raw_os_error() == Some(109) => return Ok(0),
+                        Err(e) => return Err(e)
+                    }
+                }
+
                 match listener.read(bytes)
                 {
-                    Err(e) => 
+                    Err(e) =>
                     {
                         if let Some(err) = e.raw_os_error()
                         {
@@ -228,6 +378,109 @@ impl std::io::Read for Pipe
     }
 }
 
+/// Binds and accepts a named pipe server instance at `path`, configuring its
+/// type/read-mode flags to match `mode` at bind time.
+fn bind(path: &Path, mode: PipeMode) -> Result<PipeStream>
+{
+    match mode
+    {
+        PipeMode::Byte => PipeListener::bind(path).and_then(|mut ls| ls.accept()).map_err(Error::from),
+        PipeMode::Message => bind_message_mode(path)
+    }
+}
+
+/// Binds a named pipe server instance in message mode. `windows_named_pipe`
+/// only exposes byte-stream instances, so this talks to `CreateNamedPipeW`
+/// directly and waits for a client with `ConnectNamedPipe`.
+fn bind_message_mode(path: &Path) -> Result<PipeStream>
+{
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::io::FromRawHandle;
+    use winapi::shared::winerror::ERROR_PIPE_CONNECTED;
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::namedpipeapi::{ConnectNamedPipe, CreateNamedPipeW};
+    use winapi::um::winbase::{PIPE_ACCESS_DUPLEX, PIPE_READMODE_MESSAGE, PIPE_TYPE_MESSAGE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT};
+
+    let wide_path: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+
+    let handle = unsafe
+    {
+        CreateNamedPipeW(
+            wide_path.as_ptr(),
+            PIPE_ACCESS_DUPLEX,
+            PIPE_TYPE_MESSAGE | PIPE_READMODE_MESSAGE | PIPE_WAIT,
+            PIPE_UNLIMITED_INSTANCES,
+            4096,
+            4096,
+            0,
+            std::ptr::null_mut()
+        )
+    };
+
+    if handle == INVALID_HANDLE_VALUE
+    {
+        return Err(Error::from(std::io::Error::last_os_error()));
+    }
+
+    if unsafe { ConnectNamedPipe(handle, std::ptr::null_mut()) } == 0
+    {
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() != Some(ERROR_PIPE_CONNECTED as i32)
+        {
+            unsafe { CloseHandle(handle) };
+            return Err(Error::from(err));
+        }
+    }
+
+    Ok(unsafe { PipeStream::from_raw_handle(handle as _) })
+}
+
+/// Disconnects a named pipe server instance, tearing it down so the pipe
+/// object is removed once every instance has done the same. Safe to call on
+/// an already-disconnected instance; the resulting error is harmless and can
+/// be ignored.
+fn disconnect(stream: &PipeStream) -> std::io::Result<()>
+{
+    use std::os::windows::io::AsRawHandle;
+    use winapi::um::namedpipeapi::DisconnectNamedPipe;
+
+    let result = unsafe { DisconnectNamedPipe(stream.as_raw_handle() as _) };
+    if result == 0
+    {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Returns the number of bytes currently available to read from `stream`
+/// without blocking, via `PeekNamedPipe`.
+fn peek_available(stream: &PipeStream) -> std::io::Result<u32>
+{
+    use std::os::windows::io::AsRawHandle;
+    use winapi::um::namedpipeapi::PeekNamedPipe;
+
+    let mut available: u32 = 0;
+    let result = unsafe
+    {
+        PeekNamedPipe(
+            stream.as_raw_handle() as _,
+            std::ptr::null_mut(),
+            0,
+            std::ptr::null_mut(),
+            &mut available,
+            std::ptr::null_mut()
+        )
+    };
+
+    if result == 0
+    {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(available)
+}
+
 impl Drop for Pipe
 {
     fn drop(&mut self) 
@@ -246,14 +499,150 @@ impl Clone for Pipe
 {
     /// Cloning a pipe creates a slave which points to the same path but does not
     /// close the pipe when dropped.
-    fn clone(&self) -> Self 
+    fn clone(&self) -> Self
     {
-        Pipe 
-        { 
+        Pipe
+        {
             handle: None,
             listener: None,
-            path: self.path.clone(), 
-            is_closed: true
+            path: self.path.clone(),
+            is_closed: true,
+            nonblocking: false,
+            delete_on_drop: false,
+            mode: self.mode
+        }
+    }
+}
+
+/// The read half of a `Pipe`, obtained via [`Pipe::split`] or [`Pipe::pair`].
+///
+/// Shares ownership of the server-side pipe instance with its `PipeWriter`
+/// counterpart, so the underlying `HANDLE` is only ever closed once, when
+/// both halves have been dropped. If it was created with
+/// `OnCleanup::DeleteOnDrop` (or via `pair()`), whichever half is dropped
+/// first disconnects the instance while it's still guaranteed valid.
+pub struct PipeReader
+{
+    listener: Arc<Mutex<PipeStream>>,
+    delete_on_drop: bool,
+    disconnected: Arc<AtomicBool>,
+    nonblocking: bool
+}
+
+impl PipeReader
+{
+    /// Puts the reader into (or takes it out of) non-blocking mode. Once set,
+    /// reads that would otherwise block return `ErrorKind::WouldBlock`
+    /// instead, the same as [`Pipe::set_nonblocking`].
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()>
+    {
+        self.nonblocking = nonblocking;
+        Ok(())
+    }
+}
+
+impl Read for PipeReader
+{
+    fn read(&mut self, bytes: &mut [u8]) -> std::io::Result<usize>
+    {
+        let mut listener = self.listener.lock().unwrap();
+
+        if self.nonblocking
+        {
+            match peek_available(&listener)
+            {
+                Ok(0) => return Err(std::io::Error::from(std::io::ErrorKind::WouldBlock)),
+                Ok(_) => {},
+                Err(e) if e.raw_os_error() == Some(109) => return Ok(0),
+                Err(e) => return Err(e)
+            }
+        }
+
+        match listener.read(bytes)
+        {
+            Err(e) =>
+            {
+                if let Some(err) = e.raw_os_error()
+                {
+                    if err as u32 != 109
+                    {
+                        return Err(std::io::Error::from(e));
+                    }
+                }
+                Ok(0)
+            },
+            bytes_read => bytes_read
+        }
+    }
+}
+
+impl Drop for PipeReader
+{
+    fn drop(&mut self)
+    {
+        if self.delete_on_drop && !self.disconnected.swap(true, Ordering::SeqCst)
+        {
+            let _ = disconnect(&self.listener.lock().unwrap());
+        }
+    }
+}
+
+/// The write half of a `Pipe`, obtained via [`Pipe::split`] or [`Pipe::pair`].
+///
+/// Shares ownership of the server-side pipe instance (the one that actually
+/// needs disconnecting) with its `PipeReader` counterpart via `Arc`, rather
+/// than holding a raw copy of its `HANDLE`: a raw copy would go stale the
+/// instant the other half closes it, and Windows can and does recycle
+/// `HANDLE` values. `disconnected` ensures only the half that drops first
+/// calls `DisconnectNamedPipe`, while the `Arc` guarantees the handle is
+/// still open and not yet reused by anything else when it does.
+pub struct PipeWriter
+{
+    handle: Option<PipeStream>,
+    #[allow(dead_code)]
+    path: std::path::PathBuf,
+    delete_on_drop: bool,
+    listener: Arc<Mutex<PipeStream>>,
+    disconnected: Arc<AtomicBool>
+}
+
+impl Write for PipeWriter
+{
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize>
+    {
+        match &mut self.handle
+        {
+            Some(handle) => handle.write(bytes),
+            None => unreachable!()
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()>
+    {
+        match &mut self.handle
+        {
+            Some(handle) => handle.flush(),
+            None => unreachable!()
+        }
+    }
+}
+
+impl Drop for PipeWriter
+{
+    fn drop(&mut self)
+    {
+        // Close the client-side handle before touching `listener`'s lock. A
+        // `PipeReader` on another thread may be parked inside a blocking
+        // `read()` on that same instance, holding the lock for the duration
+        // of the call; the only thing that unblocks it is this end of the
+        // connection going away (Windows surfaces that to the reader as a
+        // broken-pipe error). Locking first, as before, could deadlock
+        // forever against exactly that blocked read.
+        self.handle = None;
+
+        if self.delete_on_drop && !self.disconnected.swap(true, Ordering::SeqCst)
+        {
+            let _ = disconnect(&self.listener.lock().unwrap());
         }
     }
 }
\ No newline at end of file
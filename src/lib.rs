@@ -0,0 +1,176 @@
+//! A minimal cross platform library for interacting with named pipes.
+
+#[cfg(windows)]
+mod fifo_windows;
+#[cfg(windows)]
+pub use fifo_windows::{Pipe, PipeReader, PipeWriter};
+
+#[cfg(unix)]
+mod fifo_unix;
+#[cfg(unix)]
+pub use fifo_unix::{Pipe, PipeReader, PipeWriter};
+
+#[cfg(feature = "serde")]
+mod channel;
+#[cfg(feature = "serde")]
+pub use channel::Channel;
+
+mod read2;
+pub use read2::read2;
+
+use std::fmt;
+use std::path::Path;
+
+/// Specifies what should happen to the underlying named pipe on the filesystem
+/// when a `Pipe` (or the half of a split `Pipe` that owns it) is dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnCleanup
+{
+    /// Delete the named pipe from the filesystem.
+    DeleteOnDrop,
+    /// Leave the named pipe on the filesystem.
+    #[default]
+    NoDelete
+}
+
+/// The error type used throughout this crate.
+#[derive(Debug)]
+pub enum Error
+{
+    /// An underlying I/O operation failed.
+    Io(std::io::Error),
+    /// The pipe was closed (or the peer disconnected) before a full frame
+    /// could be read.
+    UnexpectedEof,
+    /// A message could not be serialized or deserialized.
+    #[cfg(feature = "serde")]
+    Serialization(bincode::Error),
+    /// A [`Channel`](crate::Channel) message's length prefix exceeded
+    /// [`channel::MAX_PAYLOAD_SIZE`], so the payload was rejected before
+    /// allocating a buffer for it.
+    #[cfg(feature = "serde")]
+    FrameTooLarge(usize)
+}
+
+impl fmt::Display for Error
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::UnexpectedEof => write!(f, "pipe closed before a full frame was read"),
+            #[cfg(feature = "serde")]
+            Error::Serialization(e) => write!(f, "{}", e),
+            #[cfg(feature = "serde")]
+            Error::FrameTooLarge(len) => write!(f, "frame length prefix ({} bytes) exceeds the maximum allowed payload size", len)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error
+{
+    fn from(e: std::io::Error) -> Self
+    {
+        Error::Io(e)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<bincode::Error> for Error
+{
+    fn from(e: bincode::Error) -> Self
+    {
+        Error::Serialization(e)
+    }
+}
+
+impl From<Error> for std::io::Error
+{
+    fn from(e: Error) -> Self
+    {
+        match e
+        {
+            Error::Io(e) => e,
+            Error::UnexpectedEof => std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "pipe closed before a full frame was read"),
+            #[cfg(feature = "serde")]
+            Error::Serialization(e) => std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+            #[cfg(feature = "serde")]
+            Error::FrameTooLarge(len) => std::io::Error::new(std::io::ErrorKind::InvalidData, format!("frame length prefix ({} bytes) exceeds the maximum allowed payload size", len))
+        }
+    }
+}
+
+/// A specialized `Result` type used throughout this crate.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Selects how a pipe instance delivers data to its reader.
+///
+/// Only meaningful on Windows, where a named pipe instance can be bound in
+/// either mode. Unix named pipes (FIFOs) are always byte streams, so `mode`
+/// has no effect there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PipeMode
+{
+    /// Writes are concatenated into a single continuous byte stream.
+    #[default]
+    Byte,
+    /// Each `write_bytes` call produces one discrete message, readable as a
+    /// unit on the other end.
+    Message
+}
+
+/// Builds a `Pipe` with non-default cleanup and framing behavior.
+///
+/// ```no_run
+/// use ipipe::{PipeBuilder, OnCleanup, PipeMode};
+///
+/// let pipe = PipeBuilder::new()
+///     .on_cleanup(OnCleanup::DeleteOnDrop)
+///     .mode(PipeMode::Message)
+///     .create();
+/// ```
+#[derive(Default)]
+pub struct PipeBuilder
+{
+    on_cleanup: OnCleanup,
+    mode: PipeMode
+}
+
+impl PipeBuilder
+{
+    /// Creates a builder with the same defaults as `Pipe::create`/`Pipe::open`
+    /// (`OnCleanup::NoDelete`, `PipeMode::Byte`).
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// Sets whether the named pipe is deleted from the filesystem on drop.
+    pub fn on_cleanup(mut self, on_cleanup: OnCleanup) -> Self
+    {
+        self.on_cleanup = on_cleanup;
+        self
+    }
+
+    /// Sets whether the pipe is a byte stream or delivers discrete messages.
+    pub fn mode(mut self, mode: PipeMode) -> Self
+    {
+        self.mode = mode;
+        self
+    }
+
+    /// Opens an existing pipe with this builder's settings.
+    pub fn open(self, path: &Path) -> Result<Pipe>
+    {
+        Pipe::open_with(path, self.on_cleanup, self.mode)
+    }
+
+    /// Creates a new pipe with this builder's settings.
+    pub fn create(self) -> Result<Pipe>
+    {
+        Pipe::create_with(self.on_cleanup, self.mode)
+    }
+}
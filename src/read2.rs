@@ -0,0 +1,75 @@
+//! A `read2`-style multiplexer for draining several pipes on one thread.
+
+use crate::{Error, PipeReader, Result};
+use std::io::Read;
+use std::time::Duration;
+
+/// Concurrently drains `pipes`, invoking `on_data(index, bytes, eof)` for
+/// each chunk read from each pipe, without dedicating a thread per pipe.
+///
+/// Puts every pipe into non-blocking mode, then repeatedly polls each one in
+/// turn. `on_data` is called with the index of the pipe the data came from,
+/// the freshly read bytes, and whether that pipe has reached EOF. Returns
+/// once every pipe has reported EOF.
+///
+/// Takes `PipeReader`s rather than `Pipe`s: a plain `Pipe` that auto-connects
+/// its own write handle (as `set_nonblocking` does) keeps that handle open
+/// for its own lifetime, so the pipe it reads from can never see every
+/// writer close and therefore never reaches real EOF. A `PipeReader` from
+/// `split()`/`pair()` has no such self-held writer, so EOF is reachable once
+/// its peer `PipeWriter` is dropped.
+///
+/// Every read here happens with the pipe in non-blocking mode, so a peer
+/// `PipeWriter` dropping mid-poll (the expected way a caller signals "no
+/// more data") never finds this loop parked in a long blocking OS read on
+/// the shared Windows listener instance, and so never hits the
+/// writer-drop/reader-read ordering that `PipeWriter`'s `Drop` impl guards
+/// against.
+pub fn read2(pipes: &mut [&mut PipeReader], mut on_data: impl FnMut(usize, &mut Vec<u8>, bool)) -> Result<()>
+{
+    for pipe in pipes.iter_mut()
+    {
+        pipe.set_nonblocking(true)?;
+    }
+
+    let mut done = vec![false; pipes.len()];
+    let mut buf = [0u8; 4096];
+
+    while done.iter().any(|d| !d)
+    {
+        let mut made_progress = false;
+
+        for (index, pipe) in pipes.iter_mut().enumerate()
+        {
+            if done[index]
+            {
+                continue;
+            }
+
+            match pipe.read(&mut buf)
+            {
+                Ok(0) =>
+                {
+                    done[index] = true;
+                    on_data(index, &mut Vec::new(), true);
+                    made_progress = true;
+                },
+                Ok(n) =>
+                {
+                    let mut chunk = buf[..n].to_vec();
+                    on_data(index, &mut chunk, false);
+                    made_progress = true;
+                },
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {},
+                Err(e) => return Err(Error::from(e))
+            }
+        }
+
+        if !made_progress
+        {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    Ok(())
+}
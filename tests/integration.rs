@@ -0,0 +1,214 @@
+//! Integration tests exercising `ipipe`'s public API end to end.
+
+use ipipe::{read2, OnCleanup, Pipe, PipeBuilder};
+#[cfg(windows)]
+use ipipe::PipeMode;
+use std::io::{Read, Write};
+
+/// `Pipe::split()` must connect both ends itself so the caller can hand the
+/// read half to one thread while keeping the write half, without racing a
+/// connection on a separate thread first.
+#[test]
+fn split_round_trips_data()
+{
+    let pipe = PipeBuilder::new().create().unwrap();
+    let (mut reader, mut writer) = pipe.split().unwrap();
+
+    let reader_thread = std::thread::spawn(move ||
+    {
+        let mut buf = [0u8; 5];
+        reader.read_exact(&mut buf).unwrap();
+        buf
+    });
+
+    writer.write_all(b"hello").unwrap();
+
+    let received = reader_thread.join().unwrap();
+    assert_eq!(&received, b"hello");
+}
+
+/// `Pipe::pair()` must hand back two already-connected halves with no window
+/// where only one end is initialized.
+#[test]
+fn pair_round_trips_data()
+{
+    let (mut reader, mut writer) = Pipe::pair().unwrap();
+
+    let reader_thread = std::thread::spawn(move ||
+    {
+        let mut buf = [0u8; 5];
+        reader.read_exact(&mut buf).unwrap();
+        buf
+    });
+
+    writer.write_all(b"world").unwrap();
+
+    let received = reader_thread.join().unwrap();
+    assert_eq!(&received, b"world");
+}
+
+/// Dropping a `PipeWriter` must unblock a `PipeReader` parked in a blocking
+/// `read()` on another thread, rather than deadlocking. On Windows the two
+/// halves share a mutex around the server-side pipe instance: the reader
+/// holds it for the duration of its blocking read, and the writer's drop
+/// (which disconnects that same instance for `DeleteOnDrop`) must not need
+/// to acquire it before the reader can be woken up.
+#[test]
+fn drop_writer_unblocks_reader_blocked_in_read()
+{
+    let (mut reader, writer) = Pipe::pair().unwrap();
+
+    let reader_thread = std::thread::spawn(move ||
+    {
+        let mut buf = [0u8; 1];
+        reader.read(&mut buf).unwrap()
+    });
+
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    drop(writer);
+
+    let n = reader_thread.join().unwrap();
+    assert_eq!(n, 0);
+}
+
+/// `read2` must be able to multiplex two real pipes, delivering each pipe's bytes to
+/// `on_data` tagged with the right index, and must actually return once both
+/// pipes report EOF.
+///
+/// Operating on `PipeReader`s (via `Pipe::pair`) rather than plain `Pipe`s is
+/// what makes EOF reachable at all here: a plain `Pipe` holds its own write
+/// handle open for its own lifetime, so it can never see every writer close.
+#[test]
+fn read2_interleaves_two_pipes()
+{
+    let (mut reader_a, mut writer_a) = Pipe::pair().unwrap();
+    let (mut reader_b, mut writer_b) = Pipe::pair().unwrap();
+
+    let writer_thread = std::thread::spawn(move ||
+    {
+        writer_a.write_all(b"alpha-chunk").unwrap();
+        drop(writer_a);
+        writer_b.write_all(b"beta-chunk").unwrap();
+        drop(writer_b);
+    });
+
+    let mut collected: [Vec<u8>; 2] = [Vec::new(), Vec::new()];
+    let mut eof = [false; 2];
+    read2(&mut [&mut reader_a, &mut reader_b], |index, data, is_eof|
+    {
+        collected[index].extend(data.iter());
+        eof[index] = is_eof;
+    }).unwrap();
+
+    writer_thread.join().unwrap();
+
+    assert_eq!(collected[0], b"alpha-chunk");
+    assert_eq!(collected[1], b"beta-chunk");
+    assert!(eof[0]);
+    assert!(eof[1]);
+}
+
+/// `PipeMode::Message` must preserve write boundaries: each `write_bytes`
+/// call is readable as one discrete message, rather than the byte-stream
+/// concatenation a plain `Pipe` would produce.
+#[cfg(windows)]
+#[test]
+fn message_mode_preserves_write_boundaries()
+{
+    let path = std::path::PathBuf::from(format!("\\\\.\\pipe\\ipipe_test_message_mode_{}", std::process::id()));
+
+    let mut pipe = PipeBuilder::new().mode(PipeMode::Message).open(&path).unwrap();
+
+    let writer_path = path.clone();
+    let writer_thread = std::thread::spawn(move ||
+    {
+        let mut writer = Pipe::open(&writer_path, OnCleanup::NoDelete).unwrap();
+        writer.write_bytes(b"first").unwrap();
+        writer.write_bytes(b"second-message").unwrap();
+    });
+
+    // The read buffer is larger than either message, so a short read here
+    // proves the pipe is honoring message boundaries instead of just
+    // returning however many bytes happen to be buffered.
+    let mut buf = [0u8; 64];
+
+    let n = pipe.read(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"first");
+
+    let n = pipe.read(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"second-message");
+
+    writer_thread.join().unwrap();
+}
+
+/// `OnCleanup::DeleteOnDrop` must remove the named pipe from the filesystem
+/// once the owning `Pipe` is dropped.
+#[test]
+fn delete_on_drop_removes_the_pipe()
+{
+    let path = std::env::temp_dir().join(format!("ipipe_test_delete_on_drop_{}", std::process::id()));
+
+    let pipe = PipeBuilder::new().on_cleanup(OnCleanup::DeleteOnDrop).open(&path).unwrap();
+    assert!(path.exists());
+
+    drop(pipe);
+    assert!(!path.exists());
+}
+
+/// `Channel::send`/`recv` must round-trip a payload large enough to exceed a
+/// single short `write` (regressed by `write_bytes` previously issuing one
+/// non-looping `write` call per frame).
+#[cfg(feature = "serde")]
+#[test]
+fn channel_round_trips_a_large_payload()
+{
+    use ipipe::Channel;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Message
+    {
+        bytes: Vec<u8>
+    }
+
+    let path = std::env::temp_dir().join(format!("ipipe_test_channel_{}", std::process::id()));
+    let message = Message { bytes: vec![0x5A; 64 * 1024] };
+
+    let sent = Message { bytes: message.bytes.clone() };
+    let send_path = path.clone();
+    let sender = std::thread::spawn(move ||
+    {
+        let pipe = Pipe::open(&send_path, OnCleanup::NoDelete).unwrap();
+        Channel::new(pipe).send(&sent).unwrap();
+    });
+
+    let pipe = Pipe::open(&path, OnCleanup::DeleteOnDrop).unwrap();
+    let received: Message = Channel::new(pipe).recv().unwrap();
+
+    sender.join().unwrap();
+    assert_eq!(received, message);
+}
+
+/// `Channel::recv` must reject a length prefix larger than
+/// `MAX_PAYLOAD_SIZE` instead of allocating a buffer for it.
+#[cfg(feature = "serde")]
+#[test]
+fn channel_recv_rejects_an_oversized_length_prefix()
+{
+    use ipipe::{Channel, Error};
+
+    let path = std::env::temp_dir().join(format!("ipipe_test_channel_oversized_{}", std::process::id()));
+
+    let send_path = path.clone();
+    let sender = std::thread::spawn(move ||
+    {
+        let mut pipe = Pipe::open(&send_path, OnCleanup::NoDelete).unwrap();
+        pipe.write_bytes(&u32::MAX.to_le_bytes()).unwrap();
+    });
+
+    let pipe = Pipe::open(&path, OnCleanup::DeleteOnDrop).unwrap();
+    let err = Channel::<()>::new(pipe).recv().unwrap_err();
+    assert!(matches!(err, Error::FrameTooLarge(len) if len == u32::MAX as usize));
+
+    sender.join().unwrap();
+}